@@ -0,0 +1,53 @@
+use crate::Err;
+use http::{Request, Response};
+use hyper::Body;
+use tower_service::Service;
+
+/// A thin wrapper around a hyper client, scoped to a Lambda Runtime API
+/// endpoint.
+///
+/// This is the shared core behind both the per-invocation runtime loop
+/// ([`crate::run`]) and the [`crate::extension`] registration loop: callers
+/// build requests with [`crate::requests::IntoRequest`] using only the
+/// API-relative path, and `Client` resolves them against `base` before
+/// handing them to the underlying hyper service.
+#[derive(Debug)]
+pub(crate) struct Client<S> {
+    base: http::Uri,
+    inner: S,
+}
+
+impl<S> Client<S> {
+    /// Construct a new `Client`, validating that `endpoint` parses as a URI
+    /// authority.
+    pub(crate) fn with(endpoint: &str, inner: S) -> Result<Self, Err> {
+        let base = format!("http://{}", endpoint).parse::<http::Uri>()?;
+        Ok(Client { base, inner })
+    }
+}
+
+impl<S> Service<Request<Body>> for Client<S>
+where
+    S: Service<Request<Body>, Response = Response<Body>>,
+    S::Error: Into<Err> + Send + Sync + 'static + std::error::Error,
+{
+    type Response = Response<Body>;
+    type Error = Err;
+    type Future = std::pin::Pin<Box<dyn std::future::Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut std::task::Context<'_>) -> std::task::Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, req: Request<Body>) -> Self::Future {
+        let (mut parts, body) = req.into_parts();
+        let mut base = self.base.clone().into_parts();
+        base.path_and_query = parts.uri.path_and_query().cloned();
+        parts.uri = match http::Uri::from_parts(base) {
+            Ok(uri) => uri,
+            Err(err) => return Box::pin(async move { Err(Box::new(err) as Err) }),
+        };
+        let fut = self.inner.call(Request::from_parts(parts, body));
+        Box::pin(async move { fut.await.map_err(Into::into) })
+    }
+}
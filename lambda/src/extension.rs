@@ -0,0 +1,185 @@
+//! Support for writing [Lambda Runtime
+//! Extensions](https://docs.aws.amazon.com/lambda/latest/dg/runtimes-extensions-api.html):
+//! processes that register against the `/2020-01-01/extension/...` API and
+//! are then notified of `INVOKE` and `SHUTDOWN` events alongside the
+//! function's own invocations.
+//!
+//! This reuses the same [`client::Client`](crate::client::Client) and
+//! [`IntoRequest`](crate::requests::IntoRequest) pattern as the function
+//! runtime loop in [`crate::run`], just against a different set of Runtime
+//! API paths.
+
+use crate::{client::Client, requests::IntoRequest, Err};
+use bytes::buf::BufExt;
+use http::{Method, Request, Response};
+use hyper::Body;
+use serde::Deserialize;
+use std::{env, future::Future};
+use tower_service::Service;
+
+/// An event delivered to a registered extension.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "eventType")]
+pub enum NextEvent {
+    /// The function is about to be invoked.
+    #[serde(rename = "INVOKE")]
+    Invoke(InvokeEvent),
+    /// The execution environment is about to shut down.
+    #[serde(rename = "SHUTDOWN")]
+    Shutdown(ShutdownEvent),
+}
+
+/// Details of an `INVOKE` event.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct InvokeEvent {
+    /// The AWS request ID of the invocation that triggered this event.
+    #[serde(rename = "requestId")]
+    pub request_id: String,
+    /// The deadline for the invocation, in milliseconds since the Unix epoch.
+    #[serde(rename = "deadlineMs")]
+    pub deadline_ms: u64,
+}
+
+/// Details of a `SHUTDOWN` event.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct ShutdownEvent {
+    /// Why the execution environment is shutting down.
+    #[serde(rename = "shutdownReason")]
+    pub shutdown_reason: String,
+}
+
+struct RegisterRequest<'a> {
+    extension_name: &'a str,
+}
+
+impl<'a> IntoRequest for RegisterRequest<'a> {
+    fn into_req(self) -> Result<Request<Body>, Err> {
+        let body = serde_json::to_vec(&serde_json::json!({ "events": ["INVOKE", "SHUTDOWN"] }))?;
+        let req = Request::builder()
+            .method(Method::POST)
+            .uri("/2020-01-01/extension/register")
+            .header("Lambda-Extension-Name", self.extension_name)
+            .body(Body::from(body))?;
+        Ok(req)
+    }
+}
+
+struct NextEventRequest<'a> {
+    extension_id: &'a str,
+}
+
+impl<'a> IntoRequest for NextEventRequest<'a> {
+    fn into_req(self) -> Result<Request<Body>, Err> {
+        let req = Request::builder()
+            .method(Method::GET)
+            .uri("/2020-01-01/extension/event/next")
+            .header("Lambda-Extension-Identifier", self.extension_id)
+            .body(Body::empty())?;
+        Ok(req)
+    }
+}
+
+/// Registers as a Lambda Runtime Extension named `extension_name`, then
+/// long-polls for `INVOKE`/`SHUTDOWN` events, dispatching each to
+/// `callback`. Returns once a `SHUTDOWN` event has been handled.
+///
+/// # Example
+/// ```rust,no_run
+/// type Error = Box<dyn std::error::Error + Send + Sync + 'static>;
+///
+/// #[tokio::main]
+/// async fn main() -> Result<(), Error> {
+///     lambda::extension::run("my-extension", |event| async move {
+///         println!("{:?}", event);
+///         Ok(())
+///     })
+///     .await
+/// }
+/// ```
+pub async fn run<F, Fut>(extension_name: &str, callback: F) -> Result<(), Err>
+where
+    F: FnMut(NextEvent) -> Fut,
+    Fut: Future<Output = Result<(), Err>>,
+{
+    let endpoint = env::var("AWS_LAMBDA_RUNTIME_API")?;
+    let client = Client::with(&endpoint, hyper::Client::new())?;
+    run_with_client(client, extension_name, callback).await
+}
+
+/// The body of [`run`], parameterized over the client's inner `tower::Service`
+/// so it can be exercised against a stub in tests.
+async fn run_with_client<S, F, Fut>(
+    mut client: Client<S>,
+    extension_name: &str,
+    mut callback: F,
+) -> Result<(), Err>
+where
+    S: Service<Request<Body>, Response = Response<Body>>,
+    S::Error: Into<Err> + Send + Sync + 'static + std::error::Error,
+    F: FnMut(NextEvent) -> Fut,
+    Fut: Future<Output = Result<(), Err>>,
+{
+    let req = RegisterRequest { extension_name }.into_req()?;
+    let registration: Response<Body> = client.call(req).await?;
+    let extension_id = registration
+        .headers()
+        .get("Lambda-Extension-Identifier")
+        .ok_or("registration response is missing the Lambda-Extension-Identifier header")?
+        .to_str()?
+        .to_owned();
+
+    loop {
+        let req = NextEventRequest {
+            extension_id: &extension_id,
+        }
+        .into_req()?;
+        let event = client.call(req).await?;
+        let body = hyper::body::aggregate(event.into_body()).await?;
+        let event: NextEvent = serde_json::from_reader(body.reader())?;
+
+        let is_shutdown = matches!(event, NextEvent::Shutdown(_));
+        callback(event).await?;
+        if is_shutdown {
+            return Ok(());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::support::StubService;
+
+    fn registration_response() -> Response<Body> {
+        Response::builder()
+            .header("Lambda-Extension-Identifier", "ext-id")
+            .body(Body::empty())
+            .unwrap()
+    }
+
+    fn event_response(body: &str) -> Response<Body> {
+        Response::new(Body::from(body.to_owned()))
+    }
+
+    #[tokio::test]
+    async fn dispatches_invoke_then_shutdown_and_returns() {
+        let stub = StubService::new(vec![
+            registration_response(),
+            event_response(r#"{"eventType": "INVOKE", "requestId": "req-1", "deadlineMs": 1000}"#),
+            event_response(r#"{"eventType": "SHUTDOWN", "shutdownReason": "spindown"}"#),
+        ]);
+        let client = Client::with("localhost:9001", stub).unwrap();
+
+        let mut seen = Vec::new();
+        run_with_client(client, "my-extension", |event| {
+            seen.push(event);
+            async move { Ok(()) }
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(seen.len(), 2);
+        assert!(matches!(seen[0], NextEvent::Invoke(_)));
+        assert!(matches!(seen[1], NextEvent::Shutdown(_)));
+    }
+}
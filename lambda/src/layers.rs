@@ -0,0 +1,257 @@
+//! Built-in `tower::Layer`s for wrapping the handler call passed to
+//! [`crate::run_with`].
+
+use crate::{Err, LambdaEvent};
+use futures::FutureExt;
+use std::{
+    future::Future,
+    panic::AssertUnwindSafe,
+    pin::Pin,
+    task::{Context, Poll},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use tower::Layer;
+use tower_service::Service;
+
+/// A `tower::Layer` that fails an invocation once its
+/// [`LambdaCtx`](crate::LambdaCtx) deadline has passed, instead of letting it
+/// run until the Lambda service kills the whole execution environment.
+#[derive(Debug, Clone)]
+pub struct TimeoutLayer {
+    margin: Duration,
+}
+
+impl TimeoutLayer {
+    /// Construct a new `TimeoutLayer`. `margin` is subtracted from the
+    /// remaining time before the deadline, leaving headroom to report the
+    /// timeout error before the Lambda service's own clock runs out.
+    pub fn new(margin: Duration) -> Self {
+        TimeoutLayer { margin }
+    }
+}
+
+impl<S> Layer<S> for TimeoutLayer {
+    type Service = Timeout<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        Timeout {
+            inner,
+            margin: self.margin,
+        }
+    }
+}
+
+/// The `tower::Service` produced by [`TimeoutLayer`].
+#[derive(Debug, Clone)]
+pub struct Timeout<S> {
+    inner: S,
+    margin: Duration,
+}
+
+impl<S, A> Service<LambdaEvent<A>> for Timeout<S>
+where
+    S: Service<LambdaEvent<A>> + Send + 'static,
+    S::Future: Send,
+    S::Error: Into<Err>,
+    A: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = Err;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, event: LambdaEvent<A>) -> Self::Future {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u64;
+        let remaining = event
+            .ctx
+            .deadline
+            .saturating_sub(now)
+            .saturating_sub(self.margin.as_millis() as u64);
+        let fut = self.inner.call(event);
+        Box::pin(async move {
+            match tokio::time::timeout(Duration::from_millis(remaining), fut).await {
+                Ok(res) => res.map_err(Into::into),
+                Err(_) => Err("invocation exceeded its deadline".into()),
+            }
+        })
+    }
+}
+
+/// A `tower::Layer` that catches a panicking handler future and turns it
+/// into an `Err`, so a single bad invocation doesn't abort the whole
+/// execution environment.
+#[derive(Debug, Clone, Default)]
+pub struct CatchPanicLayer {
+    _priv: (),
+}
+
+impl CatchPanicLayer {
+    /// Construct a new `CatchPanicLayer`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<S> Layer<S> for CatchPanicLayer {
+    type Service = CatchPanic<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        CatchPanic { inner }
+    }
+}
+
+/// The `tower::Service` produced by [`CatchPanicLayer`].
+#[derive(Debug, Clone)]
+pub struct CatchPanic<S> {
+    inner: S,
+}
+
+impl<S, A> Service<LambdaEvent<A>> for CatchPanic<S>
+where
+    S: Service<LambdaEvent<A>> + Send + 'static,
+    S::Future: Send,
+    S::Error: Into<Err>,
+    A: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = Err;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, event: LambdaEvent<A>) -> Self::Future {
+        let fut = self.inner.call(event);
+        Box::pin(async move {
+            match AssertUnwindSafe(fut).catch_unwind().await {
+                Ok(res) => res.map_err(Into::into),
+                Err(payload) => Err(panic_message(&payload).into()),
+            }
+        })
+    }
+}
+
+fn panic_message(payload: &(dyn std::any::Any + Send)) -> String {
+    if let Some(s) = payload.downcast_ref::<&str>() {
+        (*s).to_owned()
+    } else if let Some(s) = payload.downcast_ref::<String>() {
+        s.clone()
+    } else {
+        "handler panicked".to_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Config, LambdaCtx};
+    use tower::ServiceExt;
+
+    #[derive(Clone)]
+    struct Echo;
+
+    impl Service<LambdaEvent<String>> for Echo {
+        type Response = String;
+        type Error = Err;
+        type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+        fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, event: LambdaEvent<String>) -> Self::Future {
+            Box::pin(async move { Ok(event.payload) })
+        }
+    }
+
+    #[derive(Clone)]
+    struct Hangs;
+
+    impl Service<LambdaEvent<String>> for Hangs {
+        type Response = String;
+        type Error = Err;
+        type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+        fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, _event: LambdaEvent<String>) -> Self::Future {
+            Box::pin(futures::future::pending())
+        }
+    }
+
+    #[derive(Clone)]
+    struct Panics;
+
+    impl Service<LambdaEvent<String>> for Panics {
+        type Response = String;
+        type Error = Err;
+        type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+        fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, _event: LambdaEvent<String>) -> Self::Future {
+            Box::pin(async move { panic!("boom") })
+        }
+    }
+
+    fn event_with_deadline(deadline: u64) -> LambdaEvent<String> {
+        LambdaEvent {
+            payload: "hi".to_owned(),
+            ctx: LambdaCtx {
+                request_id: "req-id".to_owned(),
+                deadline,
+                invoked_function_arn: "arn".to_owned(),
+                xray_trace_id: None,
+                env_config: Config::default(),
+            },
+        }
+    }
+
+    fn now_millis() -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_millis() as u64
+    }
+
+    #[tokio::test]
+    async fn timeout_layer_passes_through_within_the_deadline() {
+        let mut service = TimeoutLayer::new(Duration::from_millis(0)).layer(Echo);
+        let event = event_with_deadline(now_millis() + 60_000);
+        let res = service.ready().await.unwrap().call(event).await.unwrap();
+        assert_eq!(res, "hi");
+    }
+
+    #[tokio::test]
+    async fn timeout_layer_fails_past_the_deadline() {
+        let mut service = TimeoutLayer::new(Duration::from_millis(0)).layer(Hangs);
+        let event = event_with_deadline(now_millis().saturating_sub(60_000));
+        assert!(service.ready().await.unwrap().call(event).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn catch_panic_layer_converts_a_panic_into_an_error() {
+        let mut service = CatchPanicLayer::new().layer(Panics);
+        let event = event_with_deadline(now_millis() + 60_000);
+        assert!(service.ready().await.unwrap().call(event).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn catch_panic_layer_passes_through_a_normal_result() {
+        let mut service = CatchPanicLayer::new().layer(Echo);
+        let event = event_with_deadline(now_millis() + 60_000);
+        let res = service.ready().await.unwrap().call(event).await.unwrap();
+        assert_eq!(res, "hi");
+    }
+}
@@ -32,7 +32,7 @@
 //!     Ok(event)
 //! }
 //! ```
-pub use crate::types::LambdaCtx;
+pub use crate::types::{LambdaCtx, LambdaEvent};
 use bytes::buf::BufExt;
 use client::Client;
 use http::{Request, Response};
@@ -40,19 +40,29 @@ use hyper::Body;
 pub use lambda_attributes::lambda;
 use serde::{Deserialize, Serialize};
 use std::{convert::TryFrom, env, fmt, future::Future};
+use tower::{ServiceBuilder, ServiceExt};
 use tower_service::Service;
+use tracing::{error, trace, Instrument};
 
 mod client;
+/// Support for writing Lambda Runtime Extensions.
+pub mod extension;
+pub mod layers;
 mod requests;
+/// Streaming response support, for handlers that produce their output
+/// incrementally instead of all at once.
+pub mod streaming;
 #[cfg(test)]
 mod support;
+/// An opt-in `tracing` subscriber suitable for CloudWatch Logs.
+pub mod trace;
 /// Types availible to a Lambda function.
 mod types;
 
 use requests::{EventCompletionRequest, EventErrorRequest, IntoRequest, NextEventRequest};
 use types::Diagnostic;
 
-type Err = Box<dyn std::error::Error + Send + Sync + 'static>;
+pub(crate) type Err = Box<dyn std::error::Error + Send + Sync + 'static>;
 
 /// Configuration derived from environment variables.
 #[derive(Debug, Default, Clone, PartialEq)]
@@ -97,7 +107,7 @@ pub trait Handler<A, B> {
     /// # Arguments
     /// * `event` - The data received in the invocation request
     /// * `ctx` - The context for the current invocation
-    fn call(&mut self, event: A) -> Self::Fut;
+    fn call(&mut self, event: A, ctx: LambdaCtx) -> Self::Fut;
 }
 
 /// Returns a new `HandlerFn` with the given closure.
@@ -113,15 +123,37 @@ pub struct HandlerFn<F> {
 
 impl<F, A, B, Err, Fut> Handler<A, B> for HandlerFn<F>
 where
-    F: Fn(A) -> Fut,
+    F: Fn(A, LambdaCtx) -> Fut,
     Fut: Future<Output = Result<B, Err>> + Send,
     Err: Into<Box<dyn std::error::Error + Send + Sync + 'static>> + fmt::Debug,
 {
     type Err = Err;
     type Fut = Fut;
-    fn call(&mut self, req: A) -> Self::Fut {
-        // we pass along the context here
-        (self.f)(req)
+    fn call(&mut self, req: A, ctx: LambdaCtx) -> Self::Fut {
+        (self.f)(req, ctx)
+    }
+}
+
+/// Adapts any [`Handler`] into a `tower::Service<LambdaEvent<A>>`, so that it
+/// can be wrapped in `tower::Layer`s before being handed to [`run_with`].
+struct HandlerService<F> {
+    handler: F,
+}
+
+impl<F, A, B> Service<LambdaEvent<A>> for HandlerService<F>
+where
+    F: Handler<A, B>,
+{
+    type Response = B;
+    type Error = F::Err;
+    type Future = F::Fut;
+
+    fn poll_ready(&mut self, _cx: &mut std::task::Context<'_>) -> std::task::Poll<Result<(), Self::Error>> {
+        std::task::Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, event: LambdaEvent<A>) -> Self::Future {
+        self.handler.call(event.payload, event.ctx)
     }
 }
 
@@ -142,7 +174,8 @@ where
 ///     Ok(())
 /// }
 ///
-/// async fn func(s: String) -> Result<String, Error> {
+/// async fn func(s: String, ctx: LambdaCtx) -> Result<String, Error> {
+///     println!("request {} deadline {}", ctx.request_id, ctx.deadline);
 ///     Ok(s)
 /// }
 /// ```
@@ -153,12 +186,49 @@ where
     A: for<'de> Deserialize<'de>,
     B: Serialize,
 {
-    let mut handler = handler;
+    run_with(ServiceBuilder::new(), handler).await
+}
+
+/// Like [`run`], but lets the caller wrap the handler in a stack of
+/// `tower::Layer`s — for example the [`layers::TimeoutLayer`] or
+/// [`layers::CatchPanicLayer`] — before the runtime starts polling for
+/// events.
+///
+/// # Example
+/// ```rust,no_run
+/// use lambda::{handler_fn, layers::TimeoutLayer, LambdaCtx};
+/// use std::time::Duration;
+/// use tower::ServiceBuilder;
+///
+/// type Error = Box<dyn std::error::Error + Send + Sync + 'static>;
+///
+/// #[tokio::main]
+/// async fn main() -> Result<(), Error> {
+///     let func = handler_fn(func);
+///     let layers = ServiceBuilder::new().layer(TimeoutLayer::new(Duration::from_secs(5)));
+///     lambda::run_with(layers, func).await?;
+///     Ok(())
+/// }
+///
+/// async fn func(s: String, ctx: LambdaCtx) -> Result<String, Error> {
+///     Ok(s)
+/// }
+/// ```
+pub async fn run_with<A, B, F, L>(layers: ServiceBuilder<L>, handler: F) -> Result<(), Err>
+where
+    F: Handler<A, B>,
+    L: tower::Layer<HandlerService<F>>,
+    L::Service: Service<LambdaEvent<A>, Response = B>,
+    <L::Service as Service<LambdaEvent<A>>>::Error: fmt::Debug,
+    A: for<'de> Deserialize<'de>,
+    B: Serialize,
+{
+    let mut service = layers.service(HandlerService { handler });
     let config = Config::from_env().expect("Could not load config");
     let client =
         Client::with(&config.endpoint, hyper::Client::new()).expect("Could not create client");
     let mut exec = Executor { client };
-    exec.run(&mut handler).await?;
+    exec.run(&mut service).await?;
 
     Ok(())
 }
@@ -172,44 +242,181 @@ where
     S: Service<Request<Body>, Response = Response<Body>>,
     <S as Service<Request<Body>>>::Error: Into<Err> + Send + Sync + 'static + std::error::Error,
 {
-    async fn run<A, B, F>(&mut self, handler: &mut F) -> Result<(), Err>
+    async fn run<A, B, Svc>(&mut self, service: &mut Svc) -> Result<(), Err>
     where
-        F: Handler<A, B>,
-        <F as Handler<A, B>>::Err: fmt::Debug,
+        Svc: Service<LambdaEvent<A>, Response = B>,
+        Svc::Error: fmt::Debug,
         A: for<'de> Deserialize<'de>,
         B: Serialize,
     {
         let client = &mut self.client;
         loop {
-            let req = NextEventRequest.into_req()?;
-            let event = client.call(req).await?;
-            let (parts, body) = event.into_parts();
-
-            let mut ctx = LambdaCtx::try_from(&parts.headers)?;
-            ctx.env_config = Config::from_env()?;
-            let body = hyper::body::aggregate(body).await?;
-            let body = serde_json::from_reader(body.reader())?;
-
-            let req = match handler.call(body).await {
-                Ok(res) => EventCompletionRequest {
-                    request_id: &ctx.request_id,
-                    body: serde_json::to_vec(&res)?,
+            trace!("polling for next event");
+            let (payload, ctx) = next_invocation(client).await?;
+            let request_id = ctx.request_id.clone();
+            let span = tracing::info_span!(
+                "invoke",
+                request_id = %ctx.request_id,
+                function_name = %ctx.env_config.function_name,
+                xray_trace_id = ctx.xray_trace_id.as_deref().unwrap_or_default(),
+            );
+            let result = async {
+                match service.ready().await {
+                    Ok(ready) => ready.call(LambdaEvent { payload, ctx }).await,
+                    Err(err) => Err(err),
+                }
+            }
+            .instrument(span)
+            .await;
+            let req = match result {
+                Ok(res) => {
+                    trace!(%request_id, "invocation completed");
+                    EventCompletionRequest {
+                        request_id: &request_id,
+                        body: serde_json::to_vec(&res)?,
+                    }
+                    .into_req()?
                 }
-                .into_req()?,
-                Err(err) => EventErrorRequest {
-                    request_id: &ctx.request_id,
-                    diagnostic: Diagnostic {
+                Err(err) => {
+                    let diagnostic = Diagnostic {
                         error_message: format!("{:?}", err),
                         error_type: type_name_of_val(err).to_owned(),
-                    },
+                    };
+                    error!(
+                        %request_id,
+                        error_type = %diagnostic.error_type,
+                        error_message = %diagnostic.error_message,
+                        "invocation failed"
+                    );
+                    EventErrorRequest {
+                        request_id: &request_id,
+                        diagnostic,
+                    }
+                    .into_req()?
                 }
-                .into_req()?,
             };
             client.call(req).await?;
         }
     }
 }
 
-fn type_name_of_val<T>(_: T) -> &'static str {
+pub(crate) fn type_name_of_val<T>(_: T) -> &'static str {
     std::any::type_name::<T>()
 }
+
+/// Polls the Runtime API for the next invocation and deserializes its
+/// payload. Shared by both the buffered `Executor` and the streaming
+/// executor in [`crate::streaming`].
+pub(crate) async fn next_invocation<S, A>(client: &mut Client<S>) -> Result<(A, LambdaCtx), Err>
+where
+    S: Service<Request<Body>, Response = Response<Body>>,
+    S::Error: Into<Err> + Send + Sync + 'static + std::error::Error,
+    A: for<'de> Deserialize<'de>,
+{
+    let req = NextEventRequest.into_req()?;
+    let event = client.call(req).await?;
+    let (parts, body) = event.into_parts();
+
+    let mut ctx = LambdaCtx::try_from(&parts.headers)?;
+    ctx.env_config = Config::from_env()?;
+    let body = hyper::body::aggregate(body).await?;
+    let payload = serde_json::from_reader(body.reader())?;
+
+    Ok((payload, ctx))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::support::{StubService, ENV_LOCK};
+    use std::io::Read as _;
+
+    fn set_lambda_env_vars() {
+        env::set_var("AWS_LAMBDA_RUNTIME_API", "localhost:9001");
+        env::set_var("AWS_LAMBDA_FUNCTION_NAME", "test-fn");
+        env::set_var("AWS_LAMBDA_FUNCTION_MEMORY_SIZE", "128");
+        env::set_var("AWS_LAMBDA_FUNCTION_VERSION", "$LATEST");
+        env::set_var("AWS_LAMBDA_LOG_STREAM_NAME", "stream");
+        env::set_var("AWS_LAMBDA_LOG_GROUP_NAME", "group");
+    }
+
+    fn next_event_response(body: &str) -> Response<Body> {
+        Response::builder()
+            .header("lambda-runtime-aws-request-id", "req-1")
+            .header("lambda-runtime-deadline-ms", "1000")
+            .header(
+                "lambda-runtime-invoked-function-arn",
+                "arn:aws:lambda:us-east-1:123456789012:function:test",
+            )
+            .body(Body::from(body.to_owned()))
+            .unwrap()
+    }
+
+    async fn body_as_string(req: &mut Request<Body>) -> String {
+        let body = std::mem::replace(req.body_mut(), Body::empty());
+        let mut buf = hyper::body::aggregate(body).await.unwrap();
+        let mut out = Vec::new();
+        buf.reader().read_to_end(&mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[tokio::test]
+    async fn posts_a_completion_request_on_success() {
+        let _guard = ENV_LOCK.lock().unwrap();
+        set_lambda_env_vars();
+
+        let stub = StubService::new(vec![
+            next_event_response("\"hello\""),
+            Response::new(Body::empty()),
+        ]);
+        let requests = stub.requests();
+        let client = Client::with("localhost:9001", stub).unwrap();
+        let mut exec = Executor { client };
+        let mut service = HandlerService {
+            handler: handler_fn(|event: String, _ctx: LambdaCtx| async move {
+                Ok::<_, Err>(event)
+            }),
+        };
+
+        // The stub runs dry right after the completion POST, which surfaces
+        // as an error from the *next* poll for an event -- that's expected.
+        let _ = exec.run::<String, String, _>(&mut service).await;
+
+        let mut requests = requests.lock().unwrap();
+        assert_eq!(requests.len(), 3);
+        assert_eq!(
+            requests[1].uri().path(),
+            "/2018-06-01/runtime/invocation/req-1/response"
+        );
+        assert_eq!(body_as_string(&mut requests[1]).await, "\"hello\"");
+    }
+
+    #[tokio::test]
+    async fn posts_an_error_request_when_the_handler_fails() {
+        let _guard = ENV_LOCK.lock().unwrap();
+        set_lambda_env_vars();
+
+        let stub = StubService::new(vec![
+            next_event_response("\"hello\""),
+            Response::new(Body::empty()),
+        ]);
+        let requests = stub.requests();
+        let client = Client::with("localhost:9001", stub).unwrap();
+        let mut exec = Executor { client };
+        let mut service = HandlerService {
+            handler: handler_fn(|_event: String, _ctx: LambdaCtx| async move {
+                Err::<String, Err>("boom".into())
+            }),
+        };
+
+        // As above, the third recorded request is the next poll that runs dry.
+        let _ = exec.run::<String, String, _>(&mut service).await;
+
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests.len(), 3);
+        assert_eq!(
+            requests[1].uri().path(),
+            "/2018-06-01/runtime/invocation/req-1/error"
+        );
+    }
+}
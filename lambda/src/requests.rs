@@ -0,0 +1,88 @@
+use crate::{types::Diagnostic, Err};
+use http::{Method, Request};
+use hyper::Body;
+
+/// Converts a logical Runtime API call into an `http::Request`.
+pub(crate) trait IntoRequest {
+    /// Build the `http::Request` for this call.
+    fn into_req(self) -> Result<Request<Body>, Err>;
+}
+
+/// `GET /2018-06-01/runtime/invocation/next`
+pub(crate) struct NextEventRequest;
+
+impl IntoRequest for NextEventRequest {
+    fn into_req(self) -> Result<Request<Body>, Err> {
+        let req = Request::builder()
+            .method(Method::GET)
+            .uri("/2018-06-01/runtime/invocation/next")
+            .body(Body::empty())?;
+        Ok(req)
+    }
+}
+
+/// `POST /2018-06-01/runtime/invocation/{request_id}/response`
+pub(crate) struct EventCompletionRequest<'a> {
+    pub(crate) request_id: &'a str,
+    pub(crate) body: Vec<u8>,
+}
+
+impl<'a> IntoRequest for EventCompletionRequest<'a> {
+    fn into_req(self) -> Result<Request<Body>, Err> {
+        let uri = format!(
+            "/2018-06-01/runtime/invocation/{}/response",
+            self.request_id
+        );
+        let req = Request::builder()
+            .method(Method::POST)
+            .uri(uri)
+            .body(Body::from(self.body))?;
+        Ok(req)
+    }
+}
+
+/// `POST /2018-06-01/runtime/invocation/{request_id}/response`, using the
+/// Lambda Function URL response-streaming semantics: `body` is written to
+/// the hyper request incrementally rather than being serialized up front.
+pub(crate) struct StreamingCompletionRequest<'a, B> {
+    pub(crate) request_id: &'a str,
+    pub(crate) body: B,
+}
+
+impl<'a, B> IntoRequest for StreamingCompletionRequest<'a, B>
+where
+    B: Into<Body>,
+{
+    fn into_req(self) -> Result<Request<Body>, Err> {
+        let uri = format!(
+            "/2018-06-01/runtime/invocation/{}/response",
+            self.request_id
+        );
+        let req = Request::builder()
+            .method(Method::POST)
+            .uri(uri)
+            .header("Lambda-Runtime-Function-Response-Mode", "streaming")
+            .header("Transfer-Encoding", "chunked")
+            .body(self.body.into())?;
+        Ok(req)
+    }
+}
+
+/// `POST /2018-06-01/runtime/invocation/{request_id}/error`
+pub(crate) struct EventErrorRequest<'a> {
+    pub(crate) request_id: &'a str,
+    pub(crate) diagnostic: Diagnostic,
+}
+
+impl<'a> IntoRequest for EventErrorRequest<'a> {
+    fn into_req(self) -> Result<Request<Body>, Err> {
+        let uri = format!("/2018-06-01/runtime/invocation/{}/error", self.request_id);
+        let body = serde_json::to_vec(&self.diagnostic)?;
+        let req = Request::builder()
+            .method(Method::POST)
+            .uri(uri)
+            .header("lambda-runtime-function-error-type", "Unhandled")
+            .body(Body::from(body))?;
+        Ok(req)
+    }
+}
@@ -0,0 +1,219 @@
+//! Streaming response support, for handlers whose output is produced
+//! incrementally rather than built up fully in memory before being sent.
+//!
+//! This is the streaming counterpart of the buffered request/response cycle
+//! driven by [`crate::run`]; see [`run_streaming`] for the entry point.
+
+use crate::{
+    client::Client,
+    next_invocation,
+    requests::{EventErrorRequest, IntoRequest, StreamingCompletionRequest},
+    types::Diagnostic,
+    Config, Err, LambdaCtx,
+};
+use bytes::Bytes;
+use futures::Stream;
+use http::{Request, Response};
+use hyper::Body;
+use serde::Deserialize;
+use std::{fmt, future::Future};
+use tower_service::Service;
+
+/// A trait describing an asynchronous function from an event `A` to a
+/// stream of response body chunks, rather than a single buffered response.
+pub trait StreamingHandler<A> {
+    /// Errors returned by this handler.
+    type Err;
+    /// The stream of response body chunks produced by this handler.
+    type Body: Stream<Item = Result<Bytes, Self::Err>>;
+    /// The future that resolves to the response stream.
+    type Fut: Future<Output = Result<Self::Body, Self::Err>>;
+    /// Process the incoming event and return a stream of response chunks.
+    ///
+    /// # Arguments
+    /// * `event` - The data received in the invocation request
+    /// * `ctx` - The context for the current invocation
+    fn call(&mut self, event: A, ctx: LambdaCtx) -> Self::Fut;
+}
+
+/// Returns a new `StreamingHandlerFn` wrapping the given closure.
+pub fn streaming_handler_fn<F>(f: F) -> StreamingHandlerFn<F> {
+    StreamingHandlerFn { f }
+}
+
+/// A `StreamingHandler` implemented by a closure.
+#[derive(Clone, Debug)]
+pub struct StreamingHandlerFn<F> {
+    f: F,
+}
+
+impl<F, A, Body, Err, Fut> StreamingHandler<A> for StreamingHandlerFn<F>
+where
+    F: Fn(A, LambdaCtx) -> Fut,
+    Fut: Future<Output = Result<Body, Err>> + Send,
+    Body: Stream<Item = Result<Bytes, Err>>,
+{
+    type Err = Err;
+    type Body = Body;
+    type Fut = Fut;
+
+    fn call(&mut self, event: A, ctx: LambdaCtx) -> Self::Fut {
+        (self.f)(event, ctx)
+    }
+}
+
+/// Starts the Lambda Rust runtime in streaming mode, writing each response
+/// chunk to the Runtime API as it is produced instead of aggregating the
+/// whole body in memory first.
+///
+/// # Example
+/// ```rust,no_run
+/// use futures::stream;
+/// use lambda::{streaming::streaming_handler_fn, LambdaCtx};
+///
+/// type Error = Box<dyn std::error::Error + Send + Sync + 'static>;
+///
+/// #[tokio::main]
+/// async fn main() -> Result<(), Error> {
+///     let func = streaming_handler_fn(func);
+///     lambda::streaming::run_streaming(func).await?;
+///     Ok(())
+/// }
+///
+/// async fn func(
+///     s: String,
+///     _ctx: LambdaCtx,
+/// ) -> Result<impl futures::Stream<Item = Result<bytes::Bytes, Error>>, Error> {
+///     Ok(stream::iter(vec![Ok(bytes::Bytes::from(s))]))
+/// }
+/// ```
+pub async fn run_streaming<A, H>(handler: H) -> Result<(), Err>
+where
+    H: StreamingHandler<A>,
+    H::Err: fmt::Debug + Into<Err>,
+    H::Body: Send + 'static,
+    A: for<'de> Deserialize<'de>,
+{
+    let mut handler = handler;
+    let config = Config::from_env().expect("Could not load config");
+    let client =
+        Client::with(&config.endpoint, hyper::Client::new()).expect("Could not create client");
+    let mut exec = StreamingExecutor { client };
+    exec.run(&mut handler).await
+}
+
+struct StreamingExecutor<S> {
+    client: Client<S>,
+}
+
+impl<S> StreamingExecutor<S>
+where
+    S: Service<Request<Body>, Response = Response<Body>>,
+    <S as Service<Request<Body>>>::Error: Into<Err> + Send + Sync + 'static + std::error::Error,
+{
+    async fn run<A, H>(&mut self, handler: &mut H) -> Result<(), Err>
+    where
+        H: StreamingHandler<A>,
+        H::Err: fmt::Debug + Into<Err>,
+        H::Body: Send + 'static,
+        A: for<'de> Deserialize<'de>,
+    {
+        let client = &mut self.client;
+        loop {
+            let (payload, ctx) = next_invocation(client).await?;
+            let request_id = ctx.request_id.clone();
+            match handler.call(payload, ctx).await {
+                Ok(stream) => {
+                    let req = StreamingCompletionRequest {
+                        request_id: &request_id,
+                        body: Body::wrap_stream(stream),
+                    }
+                    .into_req()?;
+                    client.call(req).await?;
+                }
+                Err(err) => {
+                    let req = EventErrorRequest {
+                        request_id: &request_id,
+                        diagnostic: Diagnostic {
+                            error_message: format!("{:?}", err),
+                            error_type: crate::type_name_of_val(err).to_owned(),
+                        },
+                    }
+                    .into_req()?;
+                    client.call(req).await?;
+                }
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::support::{StubService, ENV_LOCK};
+    use futures::stream;
+    use std::io::Read as _;
+
+    fn next_event_response(body: &str) -> Response<Body> {
+        Response::builder()
+            .header("lambda-runtime-aws-request-id", "req-1")
+            .header("lambda-runtime-deadline-ms", "1000")
+            .header(
+                "lambda-runtime-invoked-function-arn",
+                "arn:aws:lambda:us-east-1:123456789012:function:test",
+            )
+            .body(Body::from(body.to_owned()))
+            .unwrap()
+    }
+
+    async fn body_as_string(req: &mut Request<Body>) -> String {
+        let body = std::mem::replace(req.body_mut(), Body::empty());
+        let mut buf = hyper::body::aggregate(body).await.unwrap();
+        let mut out = Vec::new();
+        buf.reader().read_to_end(&mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[tokio::test]
+    async fn posts_a_streaming_completion_request_on_success() {
+        // `next_invocation` reads `Config::from_env`, so the Lambda variables
+        // must be set for the run to get as far as the completion POST.
+        let _guard = ENV_LOCK.lock().unwrap();
+        std::env::set_var("AWS_LAMBDA_RUNTIME_API", "localhost:9001");
+        std::env::set_var("AWS_LAMBDA_FUNCTION_NAME", "test-fn");
+        std::env::set_var("AWS_LAMBDA_FUNCTION_MEMORY_SIZE", "128");
+        std::env::set_var("AWS_LAMBDA_FUNCTION_VERSION", "$LATEST");
+        std::env::set_var("AWS_LAMBDA_LOG_STREAM_NAME", "stream");
+        std::env::set_var("AWS_LAMBDA_LOG_GROUP_NAME", "group");
+
+        let stub = StubService::new(vec![
+            next_event_response("\"hello\""),
+            Response::new(Body::empty()),
+        ]);
+        let requests = stub.requests();
+        let client = Client::with("localhost:9001", stub).unwrap();
+        let mut exec = StreamingExecutor { client };
+        let mut handler = streaming_handler_fn(|event: String, _ctx: LambdaCtx| async move {
+            Ok::<_, Err>(stream::iter(vec![Ok::<_, Err>(Bytes::from(event))]))
+        });
+
+        // The stub runs dry right after the completion POST, which surfaces
+        // as an error from the *next* poll for an event -- that's expected.
+        let _ = exec.run::<String, _>(&mut handler).await;
+
+        let mut requests = requests.lock().unwrap();
+        assert_eq!(requests.len(), 3);
+        assert_eq!(
+            requests[1].uri().path(),
+            "/2018-06-01/runtime/invocation/req-1/response"
+        );
+        assert_eq!(
+            requests[1]
+                .headers()
+                .get("Lambda-Runtime-Function-Response-Mode")
+                .unwrap(),
+            "streaming"
+        );
+        assert_eq!(body_as_string(&mut requests[1]).await, "hello");
+    }
+}
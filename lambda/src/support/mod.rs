@@ -0,0 +1,55 @@
+//! Test-only helpers for exercising the runtime loop without a live Lambda
+//! Runtime API endpoint.
+
+use crate::Err;
+use http::{Request, Response};
+use hyper::Body;
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+};
+use tower_service::Service;
+
+/// Serializes tests that mutate process-wide `std::env` state, since `cargo
+/// test` runs tests in the same process.
+pub(crate) static ENV_LOCK: Mutex<()> = Mutex::new(());
+
+/// A `Service` that hands back canned responses in order, recording every
+/// request it receives, for use in place of a real hyper client in tests.
+#[derive(Clone)]
+pub(crate) struct StubService {
+    responses: Arc<Mutex<std::collections::VecDeque<Response<Body>>>>,
+    requests: Arc<Mutex<Vec<Request<Body>>>>,
+}
+
+impl StubService {
+    pub(crate) fn new(responses: Vec<Response<Body>>) -> Self {
+        StubService {
+            responses: Arc::new(Mutex::new(responses.into())),
+            requests: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    /// A handle onto the requests this stub has received, in order.
+    pub(crate) fn requests(&self) -> Arc<Mutex<Vec<Request<Body>>>> {
+        self.requests.clone()
+    }
+}
+
+impl Service<Request<Body>> for StubService {
+    type Response = Response<Body>;
+    type Error = Err;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: Request<Body>) -> Self::Future {
+        self.requests.lock().unwrap().push(req);
+        let resp = self.responses.lock().unwrap().pop_front();
+        Box::pin(async move { resp.ok_or_else(|| "no more stubbed responses".into()) })
+    }
+}
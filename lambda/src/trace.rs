@@ -0,0 +1,23 @@
+//! An opt-in `tracing` subscriber configured for CloudWatch Logs, so
+//! functions get correlatable structured logs without hand-rolling a
+//! subscriber.
+//!
+//! [`crate::run`] and [`crate::run_with`] instrument each invocation with a
+//! span carrying `request_id`, `function_name`, and `xray_trace_id`; calling
+//! [`init`] before `run` renders that span and its events as one JSON object
+//! per line.
+
+use tracing_subscriber::EnvFilter;
+
+/// Installs a JSON-formatting `tracing` subscriber, reading the log level
+/// from `RUST_LOG` (defaulting to `info` if unset).
+///
+/// # Panics
+/// Panics if a global subscriber has already been installed.
+pub fn init() {
+    tracing_subscriber::fmt()
+        .json()
+        .with_env_filter(EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info")))
+        .with_target(false)
+        .init();
+}
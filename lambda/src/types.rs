@@ -0,0 +1,125 @@
+use crate::{Config, Err};
+use http::HeaderMap;
+use std::convert::TryFrom;
+
+/// Context of the invocation, including information about the caller and
+/// about the currently executing function.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct LambdaCtx {
+    /// The AWS request ID generated by the Lambda service.
+    pub request_id: String,
+    /// The execution deadline for the current invocation, in milliseconds
+    /// since the Unix epoch.
+    pub deadline: u64,
+    /// The ARN of the Lambda function, version, or alias that is specified
+    /// in the invocation.
+    pub invoked_function_arn: String,
+    /// The X-Ray trace ID for the current invocation, if active tracing is
+    /// enabled. Local emulators and functions with tracing disabled may
+    /// omit this.
+    pub xray_trace_id: Option<String>,
+    /// Configuration derived from environment variables.
+    pub env_config: Config,
+}
+
+impl TryFrom<&HeaderMap> for LambdaCtx {
+    type Error = Err;
+
+    fn try_from(headers: &HeaderMap) -> Result<Self, Self::Error> {
+        let request_id = header_as_str(headers, "lambda-runtime-aws-request-id")?.to_owned();
+        let deadline = header_as_str(headers, "lambda-runtime-deadline-ms")?.parse::<u64>()?;
+        let invoked_function_arn =
+            header_as_str(headers, "lambda-runtime-invoked-function-arn")?.to_owned();
+        let xray_trace_id = headers
+            .get("lambda-runtime-trace-id")
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_owned);
+
+        Ok(LambdaCtx {
+            request_id,
+            deadline,
+            invoked_function_arn,
+            xray_trace_id,
+            env_config: Config::default(),
+        })
+    }
+}
+
+fn header_as_str<'a>(headers: &'a HeaderMap, name: &'static str) -> Result<&'a str, Err> {
+    let value = headers
+        .get(name)
+        .ok_or_else(|| format!("missing header: {}", name))?;
+    Ok(value.to_str()?)
+}
+
+/// A single Lambda invocation, bundling the deserialized payload together
+/// with the [`LambdaCtx`] for that invocation.
+///
+/// This is the request type of the `tower::Service` that backs
+/// [`crate::run`], so `tower::Layer`s in the [`crate::layers`] module see
+/// the context alongside the payload.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LambdaEvent<A> {
+    /// The data received in the invocation request.
+    pub payload: A,
+    /// The context for the current invocation.
+    pub ctx: LambdaCtx,
+}
+
+/// Error information the runtime reports back to the Lambda service when a
+/// handler returns an error.
+#[derive(Debug, Default, Clone, PartialEq, serde::Serialize)]
+pub struct Diagnostic {
+    /// The error message.
+    pub error_message: String,
+    /// The type name of the error.
+    pub error_type: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers_with_trace_id() -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert("lambda-runtime-aws-request-id", "req-id".parse().unwrap());
+        headers.insert("lambda-runtime-deadline-ms", "1000".parse().unwrap());
+        headers.insert(
+            "lambda-runtime-invoked-function-arn",
+            "arn:aws:lambda:us-east-1:123456789012:function:test"
+                .parse()
+                .unwrap(),
+        );
+        headers.insert("lambda-runtime-trace-id", "trace-id".parse().unwrap());
+        headers
+    }
+
+    #[test]
+    fn try_from_reads_all_headers() {
+        let ctx = LambdaCtx::try_from(&headers_with_trace_id()).unwrap();
+        assert_eq!(ctx.request_id, "req-id");
+        assert_eq!(ctx.deadline, 1000);
+        assert_eq!(
+            ctx.invoked_function_arn,
+            "arn:aws:lambda:us-east-1:123456789012:function:test"
+        );
+        assert_eq!(ctx.xray_trace_id.as_deref(), Some("trace-id"));
+    }
+
+    #[test]
+    fn try_from_allows_a_missing_trace_id() {
+        let mut headers = headers_with_trace_id();
+        headers.remove("lambda-runtime-trace-id");
+
+        let ctx = LambdaCtx::try_from(&headers).unwrap();
+        assert_eq!(ctx.xray_trace_id, None);
+    }
+
+    #[test]
+    fn try_from_requires_the_request_id() {
+        let mut headers = headers_with_trace_id();
+        headers.remove("lambda-runtime-aws-request-id");
+
+        assert!(LambdaCtx::try_from(&headers).is_err());
+    }
+}